@@ -1,19 +1,45 @@
 use std::{
+    cell::RefCell,
     fmt::{Debug, Display},
     fs::File,
-    io::{self, BufReader, Read, Seek},
+    io::{self, BufReader, Read, Seek, SeekFrom},
     path::Path,
 };
 
-use no_panic::no_panic;
 use thiserror::Error;
 
+pub mod crypt;
+pub mod filter;
+pub mod linearization;
+pub mod object;
+pub mod resolve;
+pub mod xref;
+
+use crypt::{CryptError, Decryptor};
+use linearization::Linearization;
+use object::PdfObject;
+use resolve::ResolveError;
+use xref::{ObjRef, XRefParseError, XRefTable};
+
+/// How many leading bytes are scanned for the `%PDF-` signature. Real-world
+/// files sometimes carry junk before the header (an HTTP preamble, a UTF-8
+/// BOM); this mirrors what other readers tolerate.
+const HEADER_SCAN_WINDOW: usize = 1024;
+
 #[derive(Error, Debug)]
 pub enum PDFInitializationError {
     #[error("unable to open file")]
     FileOpen(#[from] io::Error),
+    #[error("no `%PDF-` signature found in the first {0} bytes, may not be a pdf file")]
+    HeaderNotFound(usize),
     #[error("could not recognize version string, may not be a pdf file")]
     BadVersionIdentifier,
+    #[error("failed to parse cross-reference table")]
+    XRef(#[from] XRefParseError),
+    #[error("failed to read the /Encrypt dictionary")]
+    EncryptDictUnreadable(#[from] ResolveError),
+    #[error("document is encrypted in a way this crate cannot decrypt")]
+    Encryption(#[from] CryptError),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -29,6 +55,32 @@ pub enum PDFVersion {
     PDF2_0,
 }
 
+impl PDFVersion {
+    fn from_digits(major: u8, minor: u8) -> Option<Self> {
+        match (major, minor) {
+            (b'1', b'0') => Some(PDFVersion::PDF1_0),
+            (b'1', b'1') => Some(PDFVersion::PDF1_1),
+            (b'1', b'2') => Some(PDFVersion::PDF1_2),
+            (b'1', b'3') => Some(PDFVersion::PDF1_3),
+            (b'1', b'4') => Some(PDFVersion::PDF1_4),
+            (b'1', b'5') => Some(PDFVersion::PDF1_5),
+            (b'1', b'6') => Some(PDFVersion::PDF1_6),
+            (b'1', b'7') => Some(PDFVersion::PDF1_7),
+            (b'2', b'0') => Some(PDFVersion::PDF2_0),
+            _ => None,
+        }
+    }
+
+    /// Parses a catalog `/Version` name such as `"1.7"`.
+    fn from_name(name: &str) -> Option<Self> {
+        let bytes = name.as_bytes();
+        match bytes {
+            [major, b'.', minor] => Self::from_digits(*major, *minor),
+            _ => None,
+        }
+    }
+}
+
 impl Display for PDFVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -51,54 +103,89 @@ pub struct PDFReader<T>
 where
     T: Debug + Read + Seek,
 {
-    #[allow(unused)]
-    inner: BufReader<T>,
-    #[allow(unused)]
+    inner: RefCell<BufReader<T>>,
     version: PDFVersion,
+    /// Byte offset of the `%PDF-` signature, in case leading junk precedes
+    /// it. Cross-reference offsets are always relative to the start of the
+    /// file regardless, so this is informational only.
+    header_offset: u64,
+    linearization: Option<Linearization>,
+    xref: XRefTable,
+    pub(crate) decryptor: Option<Decryptor>,
 }
 
 impl<T> PDFReader<T>
 where
     T: Debug + Read + Seek,
 {
-    #[no_panic]
-    fn from_bufreader(mut bf: BufReader<T>) -> Result<Self, PDFInitializationError> {
-        let mut buf = [0u8; 10];
-        bf.read(&mut buf)?;
-
-        let version = match &buf {
-            b"%PDF-1.0\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'0', b'\n', _] => {
-                Ok(PDFVersion::PDF1_0)
-            }
-            b"%PDF-1.1\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'1', b'\n', _] => {
-                Ok(PDFVersion::PDF1_1)
-            }
-            b"%PDF-1.2\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'2', b'\n', _] => {
-                Ok(PDFVersion::PDF1_2)
-            }
-            b"%PDF-1.3\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'3', b'\n', _] => {
-                Ok(PDFVersion::PDF1_3)
-            }
-            b"%PDF-1.4\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'4', b'\n', _] => {
-                Ok(PDFVersion::PDF1_4)
-            }
-            b"%PDF-1.5\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'5', b'\n', _] => {
-                Ok(PDFVersion::PDF1_5)
-            }
-            b"%PDF-1.6\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'6', b'\n', _] => {
-                Ok(PDFVersion::PDF1_6)
-            }
-            b"%PDF-1.7\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'1', b'.', b'7', b'\n', _] => {
-                Ok(PDFVersion::PDF1_7)
-            }
-            b"%PDF-2.0\r\n" | &[b'%', b'P', b'D', b'F', b'-', b'2', b'.', b'0', b'\n', _] => {
-                Ok(PDFVersion::PDF2_0)
-            }
-            _ => Err(PDFInitializationError::BadVersionIdentifier),
-        }?;
-
-        Ok(Self { inner: bf, version })
+    fn from_bufreader(bf: BufReader<T>) -> Result<Self, PDFInitializationError> {
+        Self::from_bufreader_with_password(bf, b"")
     }
+
+    fn from_bufreader_with_password(
+        mut bf: BufReader<T>,
+        password: &[u8],
+    ) -> Result<Self, PDFInitializationError> {
+        let (header_offset, version, after_header) = read_header(&mut bf)?;
+        let file_len = bf.seek(SeekFrom::End(0))?;
+        let linearization = linearization::detect(&after_header, file_len);
+
+        let xref = xref::parse(&mut bf)?;
+
+        let mut reader = Self {
+            inner: RefCell::new(bf),
+            version,
+            header_offset,
+            linearization,
+            xref,
+            decryptor: None,
+        };
+
+        if let Some(encrypt_ref) = reader.xref.encrypt {
+            // The /Encrypt dictionary's own strings are never themselves
+            // encrypted, so this fetch must happen before `decryptor` is
+            // set.
+            let encrypt_obj = reader.fetch_raw(encrypt_ref)?;
+            let encrypt_dict = encrypt_obj
+                .as_dict()
+                .ok_or(CryptError::MissingKey("Encrypt"))?;
+            reader.decryptor = Some(Decryptor::new(encrypt_dict, &reader.xref, password)?);
+        }
+
+        Ok(reader)
+    }
+}
+
+/// Scans the first [`HEADER_SCAN_WINDOW`] bytes of `bf` for the `%PDF-`
+/// signature and parses the version that follows it, tolerating leading
+/// junk (an HTTP preamble, a UTF-8 BOM) and any trailing whitespace/EOL.
+/// Also returns whatever was read past the header line, in case it holds a
+/// linearization dictionary — the first object in the file, if present.
+fn read_header<T: Read>(bf: &mut T) -> Result<(u64, PDFVersion, Vec<u8>), PDFInitializationError> {
+    let mut buf = vec![0u8; HEADER_SCAN_WINDOW];
+    let n = bf.read(&mut buf)?;
+    buf.truncate(n);
+
+    const SIGNATURE: &[u8] = b"%PDF-";
+    let pos = buf
+        .windows(SIGNATURE.len())
+        .position(|w| w == SIGNATURE)
+        .ok_or(PDFInitializationError::HeaderNotFound(HEADER_SCAN_WINDOW))?;
+
+    let after_signature = &buf[pos + SIGNATURE.len()..];
+    let version = match after_signature {
+        [major, b'.', minor, ..] => PDFVersion::from_digits(*major, *minor),
+        _ => None,
+    }
+    .ok_or(PDFInitializationError::BadVersionIdentifier)?;
+
+    let header_line_end = buf[pos..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(buf.len(), |i| pos + i + 1);
+    let after_header = buf[header_line_end..].to_vec();
+
+    Ok((pos as u64, version, after_header))
 }
 
 impl PDFReader<File> {
@@ -106,4 +193,97 @@ impl PDFReader<File> {
     pub fn from_file_path<P: AsRef<Path>>(path: P) -> Result<Self, PDFInitializationError> {
         Self::from_bufreader(BufReader::new(File::open(path)?))
     }
+
+    /// Like [`Self::from_file_path`], but for documents protected by the
+    /// standard security handler. Pass an empty slice if the document is
+    /// encrypted but has no user password.
+    pub fn from_file_path_with_password<P: AsRef<Path>>(
+        path: P,
+        password: &[u8],
+    ) -> Result<Self, PDFInitializationError> {
+        Self::from_bufreader_with_password(BufReader::new(File::open(path)?), password)
+    }
+}
+
+impl<T> PDFReader<T>
+where
+    T: Debug + Read + Seek,
+{
+    /// The version declared by the file's `%PDF-x.y` header.
+    pub fn version(&self) -> PDFVersion {
+        self.version
+    }
+
+    /// Byte offset of the `%PDF-` signature from the start of the file.
+    /// Usually `0`; nonzero when the file has leading junk before it.
+    pub fn header_offset(&self) -> u64 {
+        self.header_offset
+    }
+
+    /// The version a conforming reader should actually use: a document's
+    /// catalog `/Version` overrides the header version when later
+    /// (incremental updates often bump `/Version` without rewriting the
+    /// header). Falls back to the header version if there's no catalog, no
+    /// `/Version` entry, or it doesn't parse.
+    pub fn effective_version(&self) -> PDFVersion {
+        let catalog_version = self
+            .root()
+            .and_then(|r| self.fetch_raw(r).ok())
+            .and_then(|obj| {
+                obj.as_dict()
+                    .and_then(|dict| dict.get("Version"))
+                    .and_then(PdfObject::as_name)
+                    .and_then(PDFVersion::from_name)
+            });
+        match catalog_version {
+            Some(v) if v > self.version => v,
+            _ => self.version,
+        }
+    }
+
+    /// The linearization parameter dictionary, if the file declared one as
+    /// its first object. `None` just means the file isn't linearized, not
+    /// an error.
+    pub fn linearization(&self) -> Option<&Linearization> {
+        self.linearization.as_ref()
+    }
+
+    /// The document's `/Root` reference, as declared by the trailer (or the
+    /// most recent incremental update's trailer).
+    pub fn root(&self) -> Option<ObjRef> {
+        self.xref.root
+    }
+
+    /// The trailer's `/Size`: one greater than the highest object number
+    /// used in the file.
+    pub fn size(&self) -> u32 {
+        self.xref.size
+    }
+
+    /// Looks up where an indirect object lives in the file.
+    pub fn xref_lookup(&self, number: u32) -> Option<xref::XRefEntry> {
+        self.xref.lookup(number)
+    }
+
+    /// Resolves an indirect reference through the given cache, which may
+    /// be [`resolve::NoCache`] or (behind the `cache` feature)
+    /// [`resolve::MemoryCache`].
+    pub fn get(
+        &self,
+        r: ObjRef,
+        cache: &impl resolve::Resolve,
+    ) -> Result<object::PdfObject, ResolveError> {
+        cache.resolve(r)
+    }
+
+    /// Reads every byte from `offset` through the end of the file. Object
+    /// bodies don't declare their own length up front, so callers parse
+    /// until they hit `endobj`/`endstream` rather than slicing precisely.
+    fn read_from_offset(&self, offset: u64) -> Result<Vec<u8>, io::Error> {
+        let mut inner = self.inner.borrow_mut();
+        inner.seek(SeekFrom::Start(offset))?;
+        let mut buf = Vec::new();
+        inner.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
 }
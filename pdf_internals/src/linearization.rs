@@ -0,0 +1,81 @@
+//! Linearization ("fast web view") detection (ISO 32000 Annex F).
+//!
+//! A linearized file's very first object is a parameter dictionary marked
+//! with `/Linearized 1`, giving a reader enough to fetch the first page
+//! and the total page count without walking the full cross-reference
+//! chain. It's parsed opportunistically right after the header, before the
+//! main xref is even found, since it's just the next object in the file.
+
+use std::collections::HashMap;
+
+use crate::object::{self, NoLengthResolver, PdfObject};
+
+/// The linearization parameter dictionary, if the file declares one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Linearization {
+    /// `/L`: the file length the writer declared at linearization time.
+    pub length: u64,
+    /// `/O`: object number of the first page's `/Page` object.
+    pub first_page_object: u32,
+    /// `/E`: byte offset of the end of the first page.
+    pub end_of_first_page: Option<u64>,
+    /// `/N`: total page count.
+    pub page_count: u32,
+    /// `/T`: byte offset of the first entry of the main cross-reference
+    /// table (the one for the whole document, not the first-page one).
+    pub main_xref_offset: u64,
+    /// `/H`: hint-stream byte ranges, as `[offset, length, ...]` pairs.
+    pub hint_stream: Vec<u64>,
+    /// Whether the declared `/L` matches the file's actual length. A
+    /// mismatch usually means the download was truncated or the file has
+    /// been appended to since it was linearized.
+    pub length_matches_file: bool,
+}
+
+/// Parses the leading object of `bytes` (which should start right after
+/// the `%PDF-x.y` header line) as a linearization parameter dictionary.
+/// Returns `None` if it isn't one, rather than an error: linearization is
+/// optional, and a reader that doesn't find it just falls back to the
+/// ordinary xref chain.
+pub(crate) fn detect(bytes: &[u8], actual_file_len: u64) -> Option<Linearization> {
+    let mut cursor = bytes;
+    object::skip_whitespace_and_comments(&mut cursor);
+    object::take_integer(&mut cursor)?;
+    object::skip_whitespace(&mut cursor);
+    object::take_integer(&mut cursor)?;
+    object::skip_whitespace(&mut cursor);
+    cursor = object::strip_keyword(cursor, b"obj")?;
+
+    let value = object::parse_value(&mut cursor, &NoLengthResolver).ok()?;
+    let dict = value.as_dict()?;
+    from_dict(dict, actual_file_len)
+}
+
+fn from_dict(dict: &HashMap<String, PdfObject>, actual_file_len: u64) -> Option<Linearization> {
+    dict.get("Linearized")?;
+
+    let length = get_u64(dict, "L")?;
+    let first_page_object = get_u64(dict, "O")? as u32;
+    let page_count = get_u64(dict, "N")? as u32;
+    let main_xref_offset = get_u64(dict, "T")?;
+    let end_of_first_page = get_u64(dict, "E");
+    let hint_stream = dict
+        .get("H")
+        .and_then(PdfObject::as_array)
+        .map(|items| items.iter().filter_map(PdfObject::as_integer).map(|v| v as u64).collect())
+        .unwrap_or_default();
+
+    Some(Linearization {
+        length,
+        first_page_object,
+        end_of_first_page,
+        page_count,
+        main_xref_offset,
+        hint_stream,
+        length_matches_file: length == actual_file_len,
+    })
+}
+
+fn get_u64(dict: &HashMap<String, PdfObject>, key: &str) -> Option<u64> {
+    dict.get(key).and_then(PdfObject::as_integer).map(|v| v.max(0) as u64)
+}
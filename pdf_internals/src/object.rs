@@ -0,0 +1,632 @@
+//! Tokenizer and object model for PDF primitives.
+//!
+//! Everything in a PDF body — the page tree, content streams, font
+//! dictionaries — bottoms out in the eight object types from ISO 32000
+//! §7.3 plus indirect references. This module turns raw bytes into a
+//! typed [`PdfObject`] tree; [`crate::xref`] uses the same grammar to read
+//! the trailer dictionary.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{
+    filter::{self, Decoded, FilterError},
+    xref::ObjRef,
+};
+
+#[derive(Error, Debug)]
+pub enum ObjectParseError {
+    #[error("unexpected end of input while parsing a PDF object")]
+    UnexpectedEof,
+    #[error("malformed name literal")]
+    MalformedName,
+    #[error("unterminated literal string")]
+    UnterminatedString,
+    #[error("unterminated hex string")]
+    UnterminatedHexString,
+    #[error("dictionary key was not a name")]
+    KeyNotAName,
+    #[error("unterminated array, expected `]`")]
+    UnterminatedArray,
+    #[error("unterminated dictionary, expected `>>`")]
+    UnterminatedDictionary,
+    #[error("`stream` keyword must be followed by CRLF or a bare LF")]
+    MalformedStreamKeyword,
+    #[error("stream dictionary is missing a usable `/Length`")]
+    MissingStreamLength,
+    #[error("stream body was not followed by `endstream`")]
+    MissingEndstream,
+    #[error("expected `{0} {1} obj`")]
+    MalformedIndirectObjectHeader(&'static str, &'static str),
+    #[error("expected `endobj`")]
+    MissingEndobj,
+    #[error("unrecognized token")]
+    UnrecognizedToken,
+}
+
+/// A fully parsed PDF object. Indirect references are kept as `Reference`
+/// rather than eagerly followed; resolving one requires the xref map and
+/// is layered on by [`crate::resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PdfObject {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(f64),
+    Name(String),
+    LiteralString(Vec<u8>),
+    HexString(Vec<u8>),
+    Array(Vec<PdfObject>),
+    Dictionary(HashMap<String, PdfObject>),
+    Stream {
+        dict: HashMap<String, PdfObject>,
+        data: Vec<u8>,
+    },
+    Reference(ObjRef),
+}
+
+impl PdfObject {
+    pub fn as_dict(&self) -> Option<&HashMap<String, PdfObject>> {
+        match self {
+            PdfObject::Dictionary(d) => Some(d),
+            PdfObject::Stream { dict, .. } => Some(dict),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            PdfObject::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            PdfObject::Name(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[PdfObject]> {
+        match self {
+            PdfObject::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_reference(&self) -> Option<ObjRef> {
+        match self {
+            PdfObject::Reference(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_bytes(&self) -> Option<&[u8]> {
+        match self {
+            PdfObject::LiteralString(b) | PdfObject::HexString(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Applies this stream's `/Filter` chain and returns the decoded bytes.
+    /// Errors if the object isn't a stream at all.
+    pub fn decoded(&self) -> Result<Decoded, FilterError> {
+        match self {
+            PdfObject::Stream { dict, data } => filter::decoded(dict, data),
+            _ => Err(FilterError::NotAStream),
+        }
+    }
+}
+
+/// Resolves an indirect reference to an integer, used for `/Length` when
+/// a stream's length is itself an indirect object. [`crate::resolve`]'s
+/// `Resolve` trait is the general-purpose version of this once the object
+/// graph (and therefore cross-references between objects) can be walked.
+pub trait ResolveLength {
+    fn resolve_length(&self, r: ObjRef) -> Option<i64>;
+}
+
+/// A resolver that never resolves anything, for contexts (like the
+/// trailer) that are guaranteed not to contain streams.
+pub struct NoLengthResolver;
+
+impl ResolveLength for NoLengthResolver {
+    fn resolve_length(&self, _r: ObjRef) -> Option<i64> {
+        None
+    }
+}
+
+/// Parses a single object value: an atom, array, dictionary, or the
+/// `n g R` reference shorthand. Does not handle the `stream` keyword,
+/// since that only ever follows an indirect object's dictionary.
+pub fn parse_value(
+    cursor: &mut &[u8],
+    lengths: &impl ResolveLength,
+) -> Result<PdfObject, ObjectParseError> {
+    skip_whitespace_and_comments(cursor);
+
+    if cursor.starts_with(b"<<") {
+        return Ok(PdfObject::Dictionary(parse_dict_body(cursor, lengths)?));
+    }
+    if cursor.starts_with(b"[") {
+        *cursor = &cursor[1..];
+        let mut items = Vec::new();
+        loop {
+            skip_whitespace_and_comments(cursor);
+            if cursor.starts_with(b"]") {
+                *cursor = &cursor[1..];
+                break;
+            }
+            if cursor.is_empty() {
+                return Err(ObjectParseError::UnterminatedArray);
+            }
+            items.push(parse_value(cursor, lengths)?);
+        }
+        return Ok(PdfObject::Array(items));
+    }
+    if cursor.starts_with(b"/") {
+        return Ok(PdfObject::Name(take_name(cursor)?));
+    }
+    if cursor.starts_with(b"(") {
+        return Ok(PdfObject::LiteralString(take_literal_string(cursor)?));
+    }
+    if cursor.starts_with(b"<") {
+        return Ok(PdfObject::HexString(take_hex_string(cursor)?));
+    }
+    if let Some(rest) = strip_keyword(cursor, b"true") {
+        *cursor = rest;
+        return Ok(PdfObject::Boolean(true));
+    }
+    if let Some(rest) = strip_keyword(cursor, b"false") {
+        *cursor = rest;
+        return Ok(PdfObject::Boolean(false));
+    }
+    if let Some(rest) = strip_keyword(cursor, b"null") {
+        *cursor = rest;
+        return Ok(PdfObject::Null);
+    }
+
+    parse_number_or_reference(cursor)
+}
+
+/// Numbers and `n g R` references share a prefix (one or two integers),
+/// so they're disambiguated with a speculative two-token lookahead.
+fn parse_number_or_reference(cursor: &mut &[u8]) -> Result<PdfObject, ObjectParseError> {
+    let checkpoint = *cursor;
+    let first = take_number(cursor).ok_or(ObjectParseError::UnrecognizedToken)?;
+
+    if let PdfObject::Integer(number) = first {
+        if number >= 0 {
+            let after_first = *cursor;
+            skip_whitespace(cursor);
+            if let Some(PdfObject::Integer(generation)) = take_number(cursor) {
+                if generation >= 0 {
+                    skip_whitespace(cursor);
+                    if let Some(rest) = strip_keyword(cursor, b"R") {
+                        *cursor = rest;
+                        return Ok(PdfObject::Reference(ObjRef {
+                            number: number as u32,
+                            generation: generation as u16,
+                        }));
+                    }
+                }
+            }
+            *cursor = after_first;
+        }
+    }
+    let _ = checkpoint;
+    Ok(first)
+}
+
+fn parse_dict_body(
+    cursor: &mut &[u8],
+    lengths: &impl ResolveLength,
+) -> Result<HashMap<String, PdfObject>, ObjectParseError> {
+    debug_assert!(cursor.starts_with(b"<<"));
+    *cursor = &cursor[2..];
+
+    let mut map = HashMap::new();
+    loop {
+        skip_whitespace_and_comments(cursor);
+        if cursor.starts_with(b">>") {
+            *cursor = &cursor[2..];
+            break;
+        }
+        if !cursor.starts_with(b"/") {
+            return Err(ObjectParseError::KeyNotAName);
+        }
+        let key = take_name(cursor)?;
+        let value = parse_value(cursor, lengths)?;
+        map.insert(key, value);
+    }
+    Ok(map)
+}
+
+/// Parses a `n g obj ... endobj` indirect object, including the stream
+/// body (if any) that follows its dictionary.
+pub fn parse_indirect_object(
+    cursor: &mut &[u8],
+    expected: ObjRef,
+    lengths: &impl ResolveLength,
+) -> Result<PdfObject, ObjectParseError> {
+    skip_whitespace_and_comments(cursor);
+    let number = take_integer(cursor).ok_or(ObjectParseError::MalformedIndirectObjectHeader(
+        "n", "g",
+    ))?;
+    skip_whitespace(cursor);
+    let generation =
+        take_integer(cursor).ok_or(ObjectParseError::MalformedIndirectObjectHeader("n", "g"))?;
+    skip_whitespace(cursor);
+    *cursor =
+        strip_keyword(cursor, b"obj").ok_or(ObjectParseError::MalformedIndirectObjectHeader(
+            "n", "g",
+        ))?;
+    debug_assert_eq!(number as u32, expected.number);
+    debug_assert_eq!(generation as u16, expected.generation);
+
+    let value = parse_value(cursor, lengths)?;
+
+    skip_whitespace_and_comments(cursor);
+    let object = if let Some(rest) = strip_keyword(cursor, b"stream") {
+        *cursor = rest;
+        let dict = match value {
+            PdfObject::Dictionary(d) => d,
+            _ => return Err(ObjectParseError::MalformedStreamKeyword),
+        };
+        let data = take_stream_body(cursor, &dict, lengths)?;
+        PdfObject::Stream { dict, data }
+    } else {
+        value
+    };
+
+    skip_whitespace_and_comments(cursor);
+    if strip_keyword(cursor, b"endobj").is_none() {
+        return Err(ObjectParseError::MissingEndobj);
+    }
+
+    Ok(object)
+}
+
+fn take_stream_body(
+    cursor: &mut &[u8],
+    dict: &HashMap<String, PdfObject>,
+    lengths: &impl ResolveLength,
+) -> Result<Vec<u8>, ObjectParseError> {
+    // Per spec the `stream` keyword is followed by CRLF, or a bare LF
+    // (tolerated though non-conformant); a lone CR is not permitted.
+    if cursor.starts_with(b"\r\n") {
+        *cursor = &cursor[2..];
+    } else if cursor.starts_with(b"\n") {
+        *cursor = &cursor[1..];
+    } else {
+        return Err(ObjectParseError::MalformedStreamKeyword);
+    }
+
+    let length = match dict.get("Length") {
+        Some(PdfObject::Integer(n)) => *n,
+        Some(PdfObject::Reference(r)) => lengths
+            .resolve_length(*r)
+            .ok_or(ObjectParseError::MissingStreamLength)?,
+        _ => return Err(ObjectParseError::MissingStreamLength),
+    };
+    if length < 0 || length as usize > cursor.len() {
+        return Err(ObjectParseError::MissingStreamLength);
+    }
+
+    let (data, rest) = cursor.split_at(length as usize);
+    *cursor = rest;
+
+    skip_whitespace_and_comments(cursor);
+    *cursor =
+        strip_keyword(cursor, b"endstream").ok_or(ObjectParseError::MissingEndstream)?;
+
+    Ok(data.to_vec())
+}
+
+fn take_number(cursor: &mut &[u8]) -> Option<PdfObject> {
+    let start = *cursor;
+    let mut end = 0usize;
+    if matches!(cursor.first(), Some(b'+') | Some(b'-')) {
+        end += 1;
+    }
+    let mut saw_digit = false;
+    let mut saw_dot = false;
+    while let Some(&b) = cursor.get(end) {
+        match b {
+            b'0'..=b'9' => {
+                saw_digit = true;
+                end += 1;
+            }
+            b'.' if !saw_dot => {
+                saw_dot = true;
+                end += 1;
+            }
+            _ => break,
+        }
+    }
+    if !saw_digit && !saw_dot {
+        return None;
+    }
+    let text = std::str::from_utf8(&start[..end]).ok()?;
+    *cursor = &cursor[end..];
+    if saw_dot {
+        text.parse::<f64>().ok().map(PdfObject::Real)
+    } else {
+        text.parse::<i64>().ok().map(PdfObject::Integer)
+    }
+}
+
+pub(crate) fn take_integer(cursor: &mut &[u8]) -> Option<i64> {
+    match take_number(cursor)? {
+        PdfObject::Integer(n) => Some(n),
+        PdfObject::Real(_) => None,
+        _ => unreachable!(),
+    }
+}
+
+pub(crate) fn strip_keyword<'a>(cursor: &'a [u8], keyword: &[u8]) -> Option<&'a [u8]> {
+    let rest = cursor.strip_prefix(keyword)?;
+    // Guard against matching a prefix of a longer regular-character run,
+    // e.g. `Rect` should not be mistaken for the `R` reference keyword.
+    if rest.first().is_some_and(|&b| is_regular(b)) {
+        return None;
+    }
+    Some(rest)
+}
+
+pub(crate) fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | 0x0c | 0x00)
+}
+
+pub(crate) fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+pub(crate) fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+pub(crate) fn skip_whitespace(cursor: &mut &[u8]) {
+    while let Some(&b) = cursor.first() {
+        if is_whitespace(b) {
+            *cursor = &cursor[1..];
+        } else {
+            break;
+        }
+    }
+}
+
+pub(crate) fn skip_whitespace_and_comments(cursor: &mut &[u8]) {
+    loop {
+        skip_whitespace(cursor);
+        if cursor.first() == Some(&b'%') {
+            while let Some(&b) = cursor.first() {
+                *cursor = &cursor[1..];
+                if b == b'\n' || b == b'\r' {
+                    break;
+                }
+            }
+        } else {
+            break;
+        }
+    }
+}
+
+pub(crate) fn take_name(cursor: &mut &[u8]) -> Result<String, ObjectParseError> {
+    if cursor.first() != Some(&b'/') {
+        return Err(ObjectParseError::MalformedName);
+    }
+    let mut rest = &cursor[1..];
+    let mut out = Vec::new();
+    while let Some(&b) = rest.first() {
+        if is_whitespace(b) || is_delimiter(b) {
+            break;
+        }
+        if b == b'#' && rest.len() >= 3 && rest[1].is_ascii_hexdigit() && rest[2].is_ascii_hexdigit()
+        {
+            let hex = std::str::from_utf8(&rest[1..3]).map_err(|_| ObjectParseError::MalformedName)?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| ObjectParseError::MalformedName)?);
+            rest = &rest[3..];
+        } else {
+            out.push(b);
+            rest = &rest[1..];
+        }
+    }
+    *cursor = rest;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+pub(crate) fn take_literal_string(cursor: &mut &[u8]) -> Result<Vec<u8>, ObjectParseError> {
+    if cursor.first() != Some(&b'(') {
+        return Err(ObjectParseError::UnterminatedString);
+    }
+    let mut rest = &cursor[1..];
+    let mut depth = 1u32;
+    let mut out = Vec::new();
+    while depth > 0 {
+        match rest.first() {
+            None => return Err(ObjectParseError::UnterminatedString),
+            Some(b'(') => {
+                depth += 1;
+                out.push(b'(');
+                rest = &rest[1..];
+            }
+            Some(b')') => {
+                depth -= 1;
+                rest = &rest[1..];
+                if depth > 0 {
+                    out.push(b')');
+                }
+            }
+            Some(b'\\') => {
+                rest = &rest[1..];
+                match rest.first() {
+                    Some(b'n') => {
+                        out.push(b'\n');
+                        rest = &rest[1..];
+                    }
+                    Some(b'r') => {
+                        out.push(b'\r');
+                        rest = &rest[1..];
+                    }
+                    Some(b't') => {
+                        out.push(b'\t');
+                        rest = &rest[1..];
+                    }
+                    Some(b'b') => {
+                        out.push(0x08);
+                        rest = &rest[1..];
+                    }
+                    Some(b'f') => {
+                        out.push(0x0c);
+                        rest = &rest[1..];
+                    }
+                    Some(b @ (b'(' | b')' | b'\\')) => {
+                        out.push(*b);
+                        rest = &rest[1..];
+                    }
+                    Some(b'\n') => rest = &rest[1..],
+                    Some(b'\r') => {
+                        rest = &rest[1..];
+                        if rest.first() == Some(&b'\n') {
+                            rest = &rest[1..];
+                        }
+                    }
+                    Some(&b) if b.is_ascii_digit() => {
+                        let mut value = 0u32;
+                        let mut n = 0;
+                        while n < 3 {
+                            match rest.first() {
+                                Some(&d) if (b'0'..=b'7').contains(&d) => {
+                                    value = value * 8 + (d - b'0') as u32;
+                                    rest = &rest[1..];
+                                    n += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(value as u8);
+                    }
+                    Some(&b) => {
+                        out.push(b);
+                        rest = &rest[1..];
+                    }
+                    None => return Err(ObjectParseError::UnterminatedString),
+                }
+            }
+            Some(&b) => {
+                out.push(b);
+                rest = &rest[1..];
+            }
+        }
+    }
+    *cursor = rest;
+    Ok(out)
+}
+
+pub(crate) fn take_hex_string(cursor: &mut &[u8]) -> Result<Vec<u8>, ObjectParseError> {
+    if cursor.first() != Some(&b'<') {
+        return Err(ObjectParseError::UnterminatedHexString);
+    }
+    let mut rest = &cursor[1..];
+    let mut nibbles = Vec::new();
+    loop {
+        match rest.first() {
+            None => return Err(ObjectParseError::UnterminatedHexString),
+            Some(b'>') => {
+                rest = &rest[1..];
+                break;
+            }
+            Some(&b) if b.is_ascii_hexdigit() => {
+                nibbles.push(b);
+                rest = &rest[1..];
+            }
+            Some(_) => {
+                rest = &rest[1..];
+            }
+        }
+    }
+    if nibbles.len() % 2 == 1 {
+        nibbles.push(b'0');
+    }
+    let mut out = Vec::with_capacity(nibbles.len() / 2);
+    for pair in nibbles.chunks_exact(2) {
+        let text =
+            std::str::from_utf8(pair).map_err(|_| ObjectParseError::UnterminatedHexString)?;
+        out.push(
+            u8::from_str_radix(text, 16).map_err(|_| ObjectParseError::UnterminatedHexString)?,
+        );
+    }
+    *cursor = rest;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_string_escapes() {
+        let mut cursor: &[u8] = br"(Line1\nLine2\tTabbed \(nested\) \101\n)";
+        let s = take_literal_string(&mut cursor).unwrap();
+        assert_eq!(s, b"Line1\nLine2\tTabbed (nested) A\n");
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn literal_string_balances_unescaped_parens() {
+        let mut cursor: &[u8] = b"(outer (inner) still outer)";
+        let s = take_literal_string(&mut cursor).unwrap();
+        assert_eq!(s, b"outer (inner) still outer");
+    }
+
+    #[test]
+    fn hex_string_decodes_and_pads_odd_nibble() {
+        let mut cursor: &[u8] = b"<48656C6C6F>";
+        assert_eq!(take_hex_string(&mut cursor).unwrap(), b"Hello");
+
+        let mut cursor: &[u8] = b"<901>";
+        assert_eq!(take_hex_string(&mut cursor).unwrap(), vec![0x90, 0x10]);
+    }
+
+    #[test]
+    fn name_decodes_number_sign_escapes() {
+        let mut cursor: &[u8] = b"/A#42#23";
+        assert_eq!(take_name(&mut cursor).unwrap(), "AB#");
+    }
+
+    #[test]
+    fn parse_value_reads_dictionary_with_reference() {
+        let mut cursor: &[u8] = b"<< /Type /Catalog /Pages 3 0 R >>";
+        let value = parse_value(&mut cursor, &NoLengthResolver).unwrap();
+        let dict = value.as_dict().unwrap();
+        assert_eq!(dict.get("Type").unwrap().as_name(), Some("Catalog"));
+        assert_eq!(
+            dict.get("Pages").unwrap().as_reference(),
+            Some(ObjRef {
+                number: 3,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn parse_value_reads_nested_array() {
+        let mut cursor: &[u8] = b"[1 2.5 (a) /Name]";
+        let value = parse_value(&mut cursor, &NoLengthResolver).unwrap();
+        assert_eq!(
+            value.as_array().unwrap(),
+            &[
+                PdfObject::Integer(1),
+                PdfObject::Real(2.5),
+                PdfObject::LiteralString(b"a".to_vec()),
+                PdfObject::Name("Name".to_string()),
+            ]
+        );
+    }
+}
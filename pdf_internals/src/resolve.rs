@@ -0,0 +1,223 @@
+//! Pluggable object-resolution cache.
+//!
+//! Resolving an indirect reference means seeking into the file and parsing
+//! bytes; callers that walk the same objects repeatedly (the page tree,
+//! name trees, font resources) don't want to pay that cost more than once
+//! per object. [`Resolve`] is the seam: [`NoCache`] always re-fetches, and
+//! the `cache` feature's [`MemoryCache`] remembers what it has already
+//! parsed. Objects living inside an object stream (`/Type /ObjStm`)
+//! resolve through the same trait, transparently.
+
+use std::{
+    fmt::Debug,
+    io::{Read, Seek},
+};
+
+use thiserror::Error;
+
+use crate::{
+    crypt::CryptError,
+    filter::FilterError,
+    object::{self, NoLengthResolver, ObjectParseError, PdfObject, ResolveLength},
+    xref::{ObjRef, XRefEntry},
+    PDFReader,
+};
+
+#[cfg(feature = "cache")]
+use std::{cell::RefCell, collections::HashMap};
+
+#[derive(Error, Debug)]
+pub enum ResolveError {
+    #[error("io error while resolving an indirect object")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse object body")]
+    Parse(#[from] ObjectParseError),
+    #[error("object {0} is not listed in its object stream")]
+    UnknownObject(u32),
+    #[error("malformed object stream header")]
+    MalformedObjectStream,
+    #[error("failed to decrypt object")]
+    Decrypt(#[from] CryptError),
+    #[error("failed to decode object stream filters")]
+    Filter(#[from] FilterError),
+}
+
+/// Resolves an indirect reference to the `PdfObject` it points to.
+/// Implementations are free to cache; callers that want to share one
+/// cache across many lookups hold onto a single `impl Resolve`.
+pub trait Resolve {
+    fn resolve(&self, r: ObjRef) -> Result<PdfObject, ResolveError>;
+}
+
+/// Resolves straight from the file every time, with no memoization.
+pub struct NoCache<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    reader: &'a PDFReader<T>,
+}
+
+impl<'a, T> NoCache<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    pub fn new(reader: &'a PDFReader<T>) -> Self {
+        Self { reader }
+    }
+}
+
+impl<'a, T> Resolve for NoCache<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    fn resolve(&self, r: ObjRef) -> Result<PdfObject, ResolveError> {
+        self.reader.fetch_raw(r)
+    }
+}
+
+/// Resolves from the file on first lookup, then serves later lookups of
+/// the same `(number, generation)` from memory.
+#[cfg(feature = "cache")]
+pub struct MemoryCache<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    reader: &'a PDFReader<T>,
+    cache: RefCell<HashMap<ObjRef, PdfObject>>,
+}
+
+#[cfg(feature = "cache")]
+impl<'a, T> MemoryCache<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    pub fn new(reader: &'a PDFReader<T>) -> Self {
+        Self {
+            reader,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Drops every cached object, forcing the next lookup of each back to
+    /// the file.
+    pub fn clear(&self) {
+        self.cache.borrow_mut().clear();
+    }
+}
+
+#[cfg(feature = "cache")]
+impl<'a, T> Resolve for MemoryCache<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    fn resolve(&self, r: ObjRef) -> Result<PdfObject, ResolveError> {
+        if let Some(cached) = self.cache.borrow().get(&r) {
+            return Ok(cached.clone());
+        }
+        let object = self.reader.fetch_raw(r)?;
+        self.cache.borrow_mut().insert(r, object.clone());
+        Ok(object)
+    }
+}
+
+/// Resolves an indirect `/Length` by fetching the referenced object
+/// directly (uncached): stream lengths are looked up at most once per
+/// stream, so memoizing them isn't worth the complexity.
+pub(crate) struct DirectLengthResolver<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    pub(crate) reader: &'a PDFReader<T>,
+}
+
+impl<'a, T> ResolveLength for DirectLengthResolver<'a, T>
+where
+    T: Debug + Read + Seek,
+{
+    fn resolve_length(&self, r: ObjRef) -> Option<i64> {
+        self.reader.fetch_raw(r).ok()?.as_integer()
+    }
+}
+
+impl<T> PDFReader<T>
+where
+    T: Debug + Read + Seek,
+{
+    /// Fetches and parses the object at `r` straight from the file, with
+    /// no caching. Transparently follows into an object stream when the
+    /// cross-reference table marks `r` as compressed.
+    pub(crate) fn fetch_raw(&self, r: ObjRef) -> Result<PdfObject, ResolveError> {
+        match self.xref_lookup(r.number) {
+            None | Some(XRefEntry::Free { .. }) => Ok(PdfObject::Null),
+            Some(XRefEntry::InUse { offset, .. }) => self.fetch_in_use(r, offset),
+            Some(XRefEntry::Compressed { stream_obj, index }) => {
+                self.fetch_from_object_stream(stream_obj, index, r.number)
+            }
+        }
+    }
+
+    fn fetch_in_use(&self, r: ObjRef, offset: u64) -> Result<PdfObject, ResolveError> {
+        let bytes = self.read_from_offset(offset)?;
+        let mut cursor: &[u8] = &bytes;
+        let lengths = DirectLengthResolver { reader: self };
+        let object = object::parse_indirect_object(&mut cursor, r, &lengths)?;
+        match &self.decryptor {
+            Some(decryptor) => Ok(decryptor.decrypt_object(r, object)?),
+            None => Ok(object),
+        }
+    }
+
+    fn fetch_from_object_stream(
+        &self,
+        stream_obj: u32,
+        index: u32,
+        wanted: u32,
+    ) -> Result<PdfObject, ResolveError> {
+        let stream = self.fetch_raw(ObjRef {
+            number: stream_obj,
+            generation: 0,
+        })?;
+        let (dict, data) = match &stream {
+            PdfObject::Stream { dict, data } => (dict, data),
+            _ => return Err(ResolveError::MalformedObjectStream),
+        };
+        let data = if dict.contains_key("Filter") {
+            stream.decoded()?.data
+        } else {
+            data.clone()
+        };
+        let count = dict
+            .get("N")
+            .and_then(PdfObject::as_integer)
+            .ok_or(ResolveError::MalformedObjectStream)?;
+        let first = dict
+            .get("First")
+            .and_then(PdfObject::as_integer)
+            .ok_or(ResolveError::MalformedObjectStream)?;
+
+        let mut header: &[u8] = &data;
+        let mut offsets = Vec::with_capacity(count.max(0) as usize);
+        for _ in 0..count {
+            object::skip_whitespace_and_comments(&mut header);
+            let number =
+                object::take_integer(&mut header).ok_or(ResolveError::MalformedObjectStream)?;
+            object::skip_whitespace_and_comments(&mut header);
+            let rel_offset =
+                object::take_integer(&mut header).ok_or(ResolveError::MalformedObjectStream)?;
+            offsets.push((number as u32, rel_offset));
+        }
+
+        let rel_offset = offsets
+            .get(index as usize)
+            .filter(|(number, _)| *number == wanted)
+            .or_else(|| offsets.iter().find(|(number, _)| *number == wanted))
+            .map(|&(_, rel_offset)| rel_offset)
+            .ok_or(ResolveError::UnknownObject(wanted))?;
+
+        let start = (first + rel_offset) as usize;
+        let mut cursor = data
+            .get(start..)
+            .ok_or(ResolveError::MalformedObjectStream)?;
+        Ok(object::parse_value(&mut cursor, &NoLengthResolver)?)
+    }
+}
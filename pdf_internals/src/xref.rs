@@ -0,0 +1,577 @@
+//! Cross-reference table and trailer parsing.
+//!
+//! A PDF's cross-reference section tells a reader where every indirect
+//! object lives in the file. Rather than parsing the whole file up front,
+//! a conforming reader seeks to the end, finds the `startxref` offset, and
+//! follows it (and any `/Prev` chain) to assemble a complete object map.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use thiserror::Error;
+
+use crate::filter::{self, FilterError};
+use crate::object::{self, NoLengthResolver, ObjectParseError, PdfObject};
+
+/// The number of trailing bytes scanned for `startxref` / `%%EOF`.
+const TAIL_SCAN_WINDOW: u64 = 2048;
+
+#[derive(Error, Debug)]
+pub enum XRefParseError {
+    #[error("io error while scanning for cross-reference table")]
+    Io(#[from] io::Error),
+    #[error("could not find `startxref` keyword in the last {0} bytes of the file")]
+    StartXrefNotFound(u64),
+    #[error("`startxref` offset did not point at an `xref` table or an `/XRef` stream")]
+    InvalidXRefOffset,
+    #[error("malformed cross-reference subsection header")]
+    MalformedSubsectionHeader,
+    #[error("malformed cross-reference entry, expected 20 bytes of the form `nnnnnnnnnn ggggg n`")]
+    MalformedEntry,
+    #[error("malformed trailer dictionary")]
+    MalformedTrailer(#[from] ObjectParseError),
+    #[error("trailer is missing required `/{0}` key")]
+    MissingTrailerKey(&'static str),
+    #[error("cross-reference stream's /W field must be an array of three non-negative integers")]
+    MalformedWidths,
+    #[error("cross-reference stream's /Index field must be an array of integer pairs")]
+    MalformedIndex,
+    #[error("failed to decode cross-reference stream filters")]
+    Filter(#[from] FilterError),
+}
+
+/// A reference to an indirect object: object number plus generation number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjRef {
+    pub number: u32,
+    pub generation: u16,
+}
+
+/// Where a single object lives, according to the cross-reference table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XRefEntry {
+    /// A regular object at a byte offset from the start of the file.
+    InUse { offset: u64, generation: u16 },
+    /// A free slot; `next_free` is the object number of the next free entry
+    /// in the free list (unused by this crate beyond bookkeeping).
+    Free { next_free: u32, generation: u16 },
+    /// An object stored inside an object stream (PDF 1.5+ cross-reference
+    /// streams only), at `index` within object stream `stream_obj`.
+    Compressed { stream_obj: u32, index: u32 },
+}
+
+/// The merged cross-reference map plus the trailer keys callers need most.
+#[derive(Debug, Default, Clone)]
+pub struct XRefTable {
+    entries: HashMap<u32, XRefEntry>,
+    pub root: Option<ObjRef>,
+    pub size: u32,
+    pub encrypt: Option<ObjRef>,
+    pub id: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl XRefTable {
+    /// Looks up where object `number` lives. Later incremental updates
+    /// shadow earlier ones, so this always reflects the most recent entry.
+    pub fn lookup(&self, number: u32) -> Option<XRefEntry> {
+        self.entries.get(&number).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn merge_older(&mut self, older: XRefTable) {
+        for (number, entry) in older.entries {
+            self.entries.entry(number).or_insert(entry);
+        }
+        if self.root.is_none() {
+            self.root = older.root;
+        }
+        if self.encrypt.is_none() {
+            self.encrypt = older.encrypt;
+        }
+        if self.id.is_none() {
+            self.id = older.id;
+        }
+    }
+
+    /// Overlays a hybrid-reference file's `/XRefStm` on top of this (classic)
+    /// table. Unlike `/Prev`, `/XRefStm` is not an older section: it's the
+    /// same revision described twice, once as a classic table for readers
+    /// that predate cross-reference streams and once as an xref stream for
+    /// readers that understand `/Type /ObjStm`. Per ISO 32000 §7.5.8.4, a
+    /// reader that understands xref streams must prefer the stream's
+    /// entries, since objects living in an object stream are listed as
+    /// `Free` in the classic table (so older readers skip them) but as
+    /// `Compressed` in the stream.
+    fn merge_xrefstm(&mut self, xrefstm: XRefTable) {
+        for (number, entry) in xrefstm.entries {
+            self.entries.insert(number, entry);
+        }
+        if self.root.is_none() {
+            self.root = xrefstm.root;
+        }
+        if self.encrypt.is_none() {
+            self.encrypt = xrefstm.encrypt;
+        }
+        if self.id.is_none() {
+            self.id = xrefstm.id;
+        }
+    }
+}
+
+/// Scans backward from EOF for `startxref`, then parses the cross-reference
+/// chain (classic tables and/or xref streams) that it points to, following
+/// `/Prev` and `/XRefStm` until the whole incremental-update history has
+/// been merged.
+pub fn parse<T: Debug + Read + Seek>(inner: &mut T) -> Result<XRefTable, XRefParseError> {
+    let start_offset = find_startxref(inner)?;
+    parse_chain(inner, start_offset, &mut Vec::new())
+}
+
+fn find_startxref<T: Read + Seek>(inner: &mut T) -> Result<u64, XRefParseError> {
+    let file_len = inner.seek(SeekFrom::End(0))?;
+    let window = TAIL_SCAN_WINDOW.min(file_len);
+    inner.seek(SeekFrom::Start(file_len - window))?;
+
+    let mut tail = vec![0u8; window as usize];
+    inner.read_exact(&mut tail)?;
+
+    let needle = b"startxref";
+    let pos = tail
+        .windows(needle.len())
+        .rposition(|w| w == needle)
+        .ok_or(XRefParseError::StartXrefNotFound(window))?;
+
+    let mut rest = &tail[pos + needle.len()..];
+    object::skip_whitespace(&mut rest);
+    let offset = object::take_integer(&mut rest).ok_or(XRefParseError::StartXrefNotFound(window))?;
+    Ok(offset as u64)
+}
+
+/// Follows `/Prev` (and `/XRefStm`) recursively. `/Prev` sections are older
+/// revisions and are merged underneath the ones already seen so later
+/// updates win; `/XRefStm` describes the *same* revision as this section and
+/// is overlaid on top instead, since it's the one with `Compressed` entries
+/// for this revision's object streams (see `merge_xrefstm`).
+fn parse_chain<T: Debug + Read + Seek>(
+    inner: &mut T,
+    offset: u64,
+    visited: &mut Vec<u64>,
+) -> Result<XRefTable, XRefParseError> {
+    // Incremental-update chains are attacker-controlled; guard against a
+    // `/Prev` cycle sending us into an infinite loop.
+    if visited.contains(&offset) {
+        return Ok(XRefTable::default());
+    }
+    visited.push(offset);
+
+    inner.seek(SeekFrom::Start(offset))?;
+    let mut probe = [0u8; 4];
+    inner.read_exact(&mut probe)?;
+
+    let (mut table, prev, xrefstm) = if &probe == b"xref" {
+        parse_classic_section(inner)?
+    } else {
+        // Not literally "xref": the offset should point at an object whose
+        // dictionary has /Type /XRef, a PDF 1.5+ cross-reference stream.
+        inner.seek(SeekFrom::Start(offset))?;
+        parse_xref_stream_section(inner)?
+    };
+
+    if let Some(xrefstm_offset) = xrefstm {
+        let hybrid = parse_chain(inner, xrefstm_offset, visited)?;
+        table.merge_xrefstm(hybrid);
+    }
+    if let Some(prev_offset) = prev {
+        let older = parse_chain(inner, prev_offset, visited)?;
+        table.merge_older(older);
+    }
+
+    Ok(table)
+}
+
+/// Parses a classic `xref` table and the trailer dictionary following it.
+/// Returns the table plus the trailer's `/Prev` and `/XRefStm` offsets.
+fn parse_classic_section<T: Read + Seek>(
+    inner: &mut T,
+) -> Result<(XRefTable, Option<u64>, Option<u64>), XRefParseError> {
+    let mut table = XRefTable::default();
+
+    // Read everything from here to the end of the file in one go; parsing
+    // stops at the trailer's closing `>>`.
+    let mut buf = Vec::new();
+    inner.read_to_end(&mut buf)?;
+    let mut cursor: &[u8] = &buf;
+
+    loop {
+        object::skip_whitespace_and_comments(&mut cursor);
+        if cursor.starts_with(b"trailer") {
+            cursor = &cursor[b"trailer".len()..];
+            break;
+        }
+        let start =
+            object::take_integer(&mut cursor).ok_or(XRefParseError::MalformedSubsectionHeader)?;
+        object::skip_whitespace(&mut cursor);
+        let count =
+            object::take_integer(&mut cursor).ok_or(XRefParseError::MalformedSubsectionHeader)?;
+        object::skip_whitespace(&mut cursor);
+
+        for i in 0..count {
+            if cursor.len() < 20 {
+                return Err(XRefParseError::MalformedEntry);
+            }
+            let (entry_bytes, rest) = cursor.split_at(20);
+            cursor = rest;
+            let entry = parse_classic_entry(entry_bytes)?;
+            let number = (start + i) as u32;
+            table.entries.entry(number).or_insert(entry);
+        }
+    }
+
+    let trailer_obj = object::parse_value(&mut cursor, &NoLengthResolver)?;
+    let trailer = trailer_obj
+        .as_dict()
+        .ok_or(XRefParseError::MalformedTrailer(ObjectParseError::KeyNotAName))?;
+    apply_trailer(&mut table, trailer)?;
+
+    let prev = trailer_integer(trailer, "Prev").map(|v| v as u64);
+    let xrefstm = trailer_integer(trailer, "XRefStm").map(|v| v as u64);
+    Ok((table, prev, xrefstm))
+}
+
+fn parse_classic_entry(bytes: &[u8]) -> Result<XRefEntry, XRefParseError> {
+    let text = std::str::from_utf8(bytes).map_err(|_| XRefParseError::MalformedEntry)?;
+    let offset_or_next: u64 = text
+        .get(0..10)
+        .and_then(|s| s.trim().parse().ok())
+        .ok_or(XRefParseError::MalformedEntry)?;
+    let generation: u16 = text
+        .get(11..16)
+        .and_then(|s| s.trim().parse().ok())
+        .ok_or(XRefParseError::MalformedEntry)?;
+    match text.as_bytes().get(17) {
+        Some(b'n') => Ok(XRefEntry::InUse {
+            offset: offset_or_next,
+            generation,
+        }),
+        Some(b'f') => Ok(XRefEntry::Free {
+            next_free: offset_or_next as u32,
+            generation,
+        }),
+        _ => Err(XRefParseError::MalformedEntry),
+    }
+}
+
+/// Parses a PDF 1.5+ cross-reference stream: the `/Type /XRef` indirect
+/// object at the current position doubles as both the xref table (its
+/// decoded stream data, as fixed-width binary records) and the trailer
+/// (its dictionary carries `/Root`, `/Size`, `/Prev`, etc. directly,
+/// rather than a separate `trailer` keyword). Returns the table plus its
+/// `/Prev` offset; unlike classic sections, xref streams have no
+/// `/XRefStm` (that key only appears on a classic trailer, to point at a
+/// hybrid-reference file's xref stream).
+fn parse_xref_stream_section<T: Read + Seek>(
+    inner: &mut T,
+) -> Result<(XRefTable, Option<u64>, Option<u64>), XRefParseError> {
+    let mut buf = Vec::new();
+    inner.read_to_end(&mut buf)?;
+
+    let mut header: &[u8] = &buf;
+    let number = object::take_integer(&mut header).ok_or(XRefParseError::InvalidXRefOffset)?;
+    object::skip_whitespace(&mut header);
+    let generation = object::take_integer(&mut header).ok_or(XRefParseError::InvalidXRefOffset)?;
+    let r = ObjRef {
+        number: number.max(0) as u32,
+        generation: generation.max(0) as u16,
+    };
+
+    let mut cursor: &[u8] = &buf;
+    let stream_obj = object::parse_indirect_object(&mut cursor, r, &NoLengthResolver)
+        .map_err(|_| XRefParseError::InvalidXRefOffset)?;
+    let (dict, data) = match &stream_obj {
+        PdfObject::Stream { dict, data } => (dict, data),
+        _ => return Err(XRefParseError::InvalidXRefOffset),
+    };
+    if dict.get("Type").and_then(PdfObject::as_name) != Some("XRef") {
+        return Err(XRefParseError::InvalidXRefOffset);
+    }
+
+    let decoded = filter::decoded(dict, data)?;
+    let widths = xref_stream_widths(dict)?;
+
+    let mut table = XRefTable::default();
+    let mut entries: &[u8] = &decoded.data;
+    for (start, count) in xref_stream_index(dict)? {
+        for i in 0..count.max(0) as u32 {
+            let entry = parse_xref_stream_entry(&mut entries, widths)?;
+            table.entries.entry(start + i).or_insert(entry);
+        }
+    }
+
+    apply_trailer(&mut table, dict)?;
+    let prev = trailer_integer(dict, "Prev").map(|v| v as u64);
+    Ok((table, prev, None))
+}
+
+/// Reads and validates the `/W` array: three byte widths for the type,
+/// second, and third fields of every fixed-width record.
+fn xref_stream_widths(dict: &HashMap<String, PdfObject>) -> Result<[usize; 3], XRefParseError> {
+    let w = dict
+        .get("W")
+        .and_then(PdfObject::as_array)
+        .ok_or(XRefParseError::MissingTrailerKey("W"))?;
+    let mut widths = [0usize; 3];
+    if w.len() != widths.len() {
+        return Err(XRefParseError::MalformedWidths);
+    }
+    for (slot, value) in widths.iter_mut().zip(w) {
+        *slot = value
+            .as_integer()
+            .filter(|n| *n >= 0)
+            .ok_or(XRefParseError::MalformedWidths)? as usize;
+    }
+    Ok(widths)
+}
+
+/// Reads the `/Index` array of `(first_object, count)` subsection pairs,
+/// defaulting to the single subsection `[0 /Size]` when absent.
+fn xref_stream_index(dict: &HashMap<String, PdfObject>) -> Result<Vec<(u32, i64)>, XRefParseError> {
+    match dict.get("Index").and_then(PdfObject::as_array) {
+        Some(items) => items
+            .chunks(2)
+            .map(|pair| match pair {
+                [start, count] => Ok((
+                    start.as_integer().ok_or(XRefParseError::MalformedIndex)? as u32,
+                    count.as_integer().ok_or(XRefParseError::MalformedIndex)?,
+                )),
+                _ => Err(XRefParseError::MalformedIndex),
+            })
+            .collect(),
+        None => {
+            let size =
+                trailer_integer(dict, "Size").ok_or(XRefParseError::MissingTrailerKey("Size"))?;
+            Ok(vec![(0, size)])
+        }
+    }
+}
+
+/// Parses one fixed-width record (`/W` gives the byte width of each of its
+/// three fields) into an [`XRefEntry`]. A width-0 type field defaults to 1
+/// (in-use), per spec.
+fn parse_xref_stream_entry(
+    cursor: &mut &[u8],
+    [w0, w1, w2]: [usize; 3],
+) -> Result<XRefEntry, XRefParseError> {
+    let field_type = if w0 == 0 { 1 } else { read_be_field(cursor, w0)? };
+    let field2 = read_be_field(cursor, w1)?;
+    let field3 = read_be_field(cursor, w2)?;
+
+    Ok(match field_type {
+        0 => XRefEntry::Free {
+            next_free: field2 as u32,
+            generation: field3 as u16,
+        },
+        1 => XRefEntry::InUse {
+            offset: field2,
+            generation: field3 as u16,
+        },
+        2 => XRefEntry::Compressed {
+            stream_obj: field2 as u32,
+            index: field3 as u32,
+        },
+        _ => return Err(XRefParseError::MalformedEntry),
+    })
+}
+
+fn read_be_field(cursor: &mut &[u8], width: usize) -> Result<u64, XRefParseError> {
+    if width == 0 {
+        return Ok(0);
+    }
+    if cursor.len() < width {
+        return Err(XRefParseError::MalformedEntry);
+    }
+    let (bytes, rest) = cursor.split_at(width);
+    *cursor = rest;
+    Ok(bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+}
+
+fn apply_trailer(
+    table: &mut XRefTable,
+    trailer: &HashMap<String, PdfObject>,
+) -> Result<(), XRefParseError> {
+    table.root = trailer
+        .get("Root")
+        .and_then(PdfObject::as_reference)
+        .ok_or(XRefParseError::MissingTrailerKey("Root"))?
+        .into();
+    table.size = trailer_integer(trailer, "Size")
+        .ok_or(XRefParseError::MissingTrailerKey("Size"))? as u32;
+    table.encrypt = trailer.get("Encrypt").and_then(PdfObject::as_reference);
+    table.id = match trailer.get("ID").and_then(PdfObject::as_array) {
+        Some([a, b]) => match (a.as_string_bytes(), b.as_string_bytes()) {
+            (Some(a), Some(b)) => Some((a.to_vec(), b.to_vec())),
+            _ => None,
+        },
+        _ => None,
+    };
+    Ok(())
+}
+
+fn trailer_integer(trailer: &HashMap<String, PdfObject>, key: &str) -> Option<i64> {
+    trailer.get(key).and_then(PdfObject::as_integer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn classic_table_and_trailer() {
+        let mut buf = b"xref\n0 3\n0000000000 65535 f \n0000000010 00000 n \n0000000020 00000 n \ntrailer\n<< /Size 3 /Root 1 0 R >>\n".to_vec();
+        buf.extend_from_slice(b"startxref\n0\n%%EOF");
+
+        let table = parse(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(
+            table.lookup(0),
+            Some(XRefEntry::Free {
+                next_free: 0,
+                generation: 65535
+            })
+        );
+        assert_eq!(
+            table.lookup(1),
+            Some(XRefEntry::InUse {
+                offset: 10,
+                generation: 0
+            })
+        );
+        assert_eq!(table.size, 3);
+        assert_eq!(
+            table.root,
+            Some(ObjRef {
+                number: 1,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn incremental_update_shadows_older_entries_via_prev() {
+        let mut buf = b"xref\n0 2\n0000000000 65535 f \n0000000100 00000 n \ntrailer\n<< /Size 2 /Root 1 0 R >>\n".to_vec();
+        let update_offset = buf.len() as u64;
+        buf.extend_from_slice(
+            b"xref\n0 2\n0000000000 65535 f \n0000000200 00000 n \ntrailer\n<< /Size 2 /Root 1 0 R /Prev 0 >>\n",
+        );
+        buf.extend_from_slice(format!("startxref\n{update_offset}\n%%EOF").as_bytes());
+
+        let table = parse(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(
+            table.lookup(1),
+            Some(XRefEntry::InUse {
+                offset: 200,
+                generation: 0
+            })
+        );
+    }
+
+    #[test]
+    fn xrefstm_compressed_entry_shadows_classic_free_entry() {
+        // A hybrid-reference file: the classic table lists object 1 as free
+        // (so pre-1.5 readers skip it), while the /XRefStm it points at
+        // lists the same object as living in object stream 5. A reader that
+        // understands xref streams must end up with the Compressed entry.
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0]); // object 0: free
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&65535u16.to_be_bytes());
+        data.extend_from_slice(&[2]); // object 1: compressed, in stream 5 at index 0
+        data.extend_from_slice(&5u32.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+
+        let mut buf = Vec::new();
+        let stream_offset = buf.len() as u64;
+        buf.extend_from_slice(
+            format!(
+                "9 0 obj\n<< /Type /XRef /W [1 4 2] /Size 2 /Root 1 0 R /Length {} >>\nstream\n",
+                data.len()
+            )
+            .as_bytes(),
+        );
+        buf.extend_from_slice(&data);
+        buf.extend_from_slice(b"\nendstream\nendobj\n");
+
+        let classic_offset = buf.len() as u64;
+        buf.extend_from_slice(
+            format!(
+                "xref\n0 2\n0000000000 65535 f \n0000000000 00000 f \ntrailer\n<< /Size 2 /Root 1 0 R /XRefStm {stream_offset} >>\n"
+            )
+            .as_bytes(),
+        );
+        buf.extend_from_slice(format!("startxref\n{classic_offset}\n%%EOF").as_bytes());
+
+        let table = parse(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(
+            table.lookup(1),
+            Some(XRefEntry::Compressed {
+                stream_obj: 5,
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn xref_stream_decodes_fixed_width_records() {
+        // /W [1 4 2]: a 1-byte type, a 4-byte second field, a 2-byte third.
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0]); // free
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&65535u16.to_be_bytes());
+        data.extend_from_slice(&[1]); // in use, object 1
+        data.extend_from_slice(&50u32.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&[1]); // in use, object 2
+        data.extend_from_slice(&100u32.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        assert_eq!(data.len(), 21);
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(
+            format!(
+                "7 0 obj\n<< /Type /XRef /W [1 4 2] /Size 3 /Root 1 0 R /Length {} >>\nstream\n",
+                data.len()
+            )
+            .as_bytes(),
+        );
+        buf.extend_from_slice(&data);
+        buf.extend_from_slice(b"\nendstream\nendobj\n");
+        buf.extend_from_slice(b"startxref\n0\n%%EOF");
+
+        let table = parse(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(
+            table.lookup(1),
+            Some(XRefEntry::InUse {
+                offset: 50,
+                generation: 0
+            })
+        );
+        assert_eq!(
+            table.lookup(2),
+            Some(XRefEntry::InUse {
+                offset: 100,
+                generation: 0
+            })
+        );
+        assert_eq!(table.size, 3);
+    }
+}